@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context};
+use monitor_client::MonitorClient;
+use serde::Deserialize;
+use tokio::time::sleep;
+use tracing::info;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WaitHealthyConfig {
+  pub timeout_secs: u64,
+  #[serde(default = "default_poll_interval_secs")]
+  pub poll_interval_secs: u64,
+}
+
+fn default_poll_interval_secs() -> u64 {
+  2
+}
+
+pub async fn wait_for_healthy(
+  client: &MonitorClient,
+  deployment_id: &str,
+  config: &WaitHealthyConfig,
+) -> anyhow::Result<()> {
+  let timeout = Duration::from_secs(config.timeout_secs);
+  let poll_interval = Duration::from_secs(config.poll_interval_secs);
+  let start = Instant::now();
+
+  loop {
+    let state = client
+      .list_deployments(None)
+      .await
+      .context("failed to poll deployment state")?
+      .into_iter()
+      .find(|d| d.deployment.id == deployment_id)
+      .map(|d| d.state)
+      .with_context(|| {
+        format!("deployment {deployment_id} not found while waiting for it to become healthy")
+      })?;
+
+    match state.to_string().to_lowercase().as_str() {
+      "running" => return Ok(()),
+      "exited" | "dead" => {
+        return Err(anyhow!(
+          "deployment {deployment_id} exited while waiting for it to become healthy (state: {state})"
+        ))
+      }
+      _ => {}
+    }
+
+    if start.elapsed() >= timeout {
+      return Err(anyhow!(
+        "deployment {deployment_id} did not become healthy within {}s (last state: {state})",
+        config.timeout_secs
+      ));
+    }
+
+    info!("waiting for {deployment_id} to become healthy... (state: {state})");
+    sleep(poll_interval).await;
+  }
+}
@@ -1,10 +1,29 @@
-use std::{collections::HashMap, fs, io::Read, path::Path};
+use std::{
+  collections::{HashMap, HashSet},
+  fs,
+  io::Read,
+  path::Path,
+  time::Instant,
+};
 
 use anyhow::{anyhow, Context};
-use monitor_client::{futures_util::future::join_all, MonitorClient};
+use monitor_client::{
+  futures_util::{
+    future::join_all,
+    stream::{FuturesUnordered, StreamExt},
+  },
+  MonitorClient,
+};
 use tracing::info;
 
-use crate::{Action, CredsFile, MonitorFile, Stage};
+use crate::{
+  health::{self, WaitHealthyConfig},
+  notifier::{notify_all, NotifyEvent, Notifier},
+  report::{EnvironmentInfo, Report, StageReport, TargetTiming},
+  scripting::{parse_target, NamedId, ScriptContext, ScriptEngine, StageOutcome, TargetSpec},
+  store::Store,
+  Action, CredsFile, MonitorFile, Stage,
+};
 
 pub fn parse_monitor_file(path: impl AsRef<Path>) -> anyhow::Result<MonitorFile> {
   let contents = fs::read_to_string(path).context("failed to read file contents")?;
@@ -26,46 +45,656 @@ pub fn wait_for_enter() -> anyhow::Result<()> {
   Ok(())
 }
 
-pub async fn run_stages(client: &MonitorClient, stages: Vec<Stage>) -> anyhow::Result<()> {
-  // info!("running monitor file: {name}");
+#[allow(clippy::too_many_arguments)]
+pub async fn run_stages(
+  client: &MonitorClient,
+  monitor_name: &str,
+  monitor_path: &Path,
+  stages: Vec<Stage>,
+  notifiers: &[Box<dyn Notifier>],
+  store: &Store,
+  report_path: Option<&Path>,
+  rollback_enabled: bool,
+) -> anyhow::Result<()> {
+  notify_all(
+    notifiers,
+    NotifyEvent::RunStarted {
+      monitor: monitor_name.to_string(),
+    },
+  )
+  .await;
+
+  let run_id = store
+    .start_run(monitor_name, &monitor_path.to_string_lossy())
+    .context("failed to record run start")?;
+
+  let stage_reports = match run_stages_inner(
+    client,
+    monitor_name,
+    stages,
+    notifiers,
+    store,
+    run_id,
+    rollback_enabled,
+  )
+  .await
+  {
+    Ok(stage_reports) => stage_reports,
+    Err(error) => {
+      store
+        .finish_run(run_id, "failed")
+        .context("failed to record run failure")?;
+      notify_all(notifiers, NotifyEvent::aborted(monitor_name, &error)).await;
+      return Err(error);
+    }
+  };
+
+  store
+    .finish_run(run_id, "success")
+    .context("failed to record run completion")?;
+
+  if let Some(report_path) = report_path {
+    let report = Report {
+      environment: EnvironmentInfo::collect(monitor_path),
+      monitor_name: monitor_name.to_string(),
+      stages: stage_reports,
+    };
+    report
+      .write(report_path)
+      .context("failed to write stage timing report")?;
+  }
+
+  notify_all(
+    notifiers,
+    NotifyEvent::RunFinished {
+      monitor: monitor_name.to_string(),
+    },
+  )
+  .await;
+
+  Ok(())
+}
+
+fn validate_stage_graph(stages: &[Stage]) -> anyhow::Result<()> {
+  let mut names = HashSet::new();
+  for stage in stages {
+    if !names.insert(stage.name.as_str()) {
+      return Err(anyhow!("duplicate stage name: {}", stage.name));
+    }
+  }
+
+  for stage in stages {
+    for dep in &stage.depends_on {
+      if !names.contains(dep.as_str()) {
+        return Err(anyhow!(
+          "stage {} depends_on unknown stage {dep}",
+          stage.name
+        ));
+      }
+    }
+  }
+
+  #[derive(PartialEq)]
+  enum Mark {
+    Visiting,
+    Done,
+  }
+  let mut marks: HashMap<&str, Mark> = HashMap::new();
+  let by_name: HashMap<&str, &Stage> = stages.iter().map(|s| (s.name.as_str(), s)).collect();
+
+  fn visit<'a>(
+    stage: &'a Stage,
+    by_name: &HashMap<&'a str, &'a Stage>,
+    marks: &mut HashMap<&'a str, Mark>,
+  ) -> anyhow::Result<()> {
+    match marks.get(stage.name.as_str()) {
+      Some(Mark::Done) => return Ok(()),
+      Some(Mark::Visiting) => {
+        return Err(anyhow!(
+          "stage dependency cycle detected at stage {}",
+          stage.name
+        ))
+      }
+      None => {}
+    }
+    marks.insert(stage.name.as_str(), Mark::Visiting);
+    for dep in &stage.depends_on {
+      visit(by_name[dep.as_str()], by_name, marks)?;
+    }
+    marks.insert(stage.name.as_str(), Mark::Done);
+    Ok(())
+  }
+
+  for stage in stages {
+    visit(stage, &by_name, &mut marks)?;
+  }
+
+  Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_stages_inner(
+  client: &MonitorClient,
+  monitor_name: &str,
+  stages: Vec<Stage>,
+  notifiers: &[Box<dyn Notifier>],
+  store: &Store,
+  run_id: i64,
+  rollback_enabled: bool,
+) -> anyhow::Result<Vec<StageReport>> {
+  validate_stage_graph(&stages)?;
+
   let build_map = build_name_to_id_map(client).await?;
   let deployment_map = deployment_name_to_id_map(client).await?;
-  for Stage {
+  let server_map = server_name_to_id_map(client).await?;
+  let by_name: HashMap<&str, &Stage> = stages.iter().map(|s| (s.name.as_str(), s)).collect();
+
+  let script_engine = ScriptEngine::new();
+  let script_base = ScriptContext {
+    stages: HashMap::new(),
+    builds: to_named_ids(&build_map),
+    deployments: to_named_ids(&deployment_map),
+    servers: to_named_ids(&server_map),
+  };
+
+  let mut pending: HashSet<String> = stages.iter().map(|s| s.name.clone()).collect();
+  let mut completed: HashSet<String> = HashSet::new();
+  let mut failed: Vec<String> = Vec::new();
+  let mut skipped: HashSet<String> = HashSet::new();
+  let mut stage_outcomes: HashMap<String, StageOutcome> = HashMap::new();
+  let mut stage_reports: Vec<StageReport> = Vec::new();
+  let mut rollback_steps: Vec<RollbackStep> = Vec::new();
+  let mut aborted = false;
+
+  let mut running = FuturesUnordered::new();
+
+  loop {
+    if !aborted {
+      let ready = pending
+        .iter()
+        .filter(|name| {
+          by_name[name.as_str()]
+            .depends_on
+            .iter()
+            .all(|dep| completed.contains(dep))
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+      for name in ready {
+        pending.remove(&name);
+        let stage = by_name[name.as_str()];
+        let script_ctx = ScriptContext {
+          stages: stage_outcomes.clone(),
+          builds: script_base.builds.clone(),
+          deployments: script_base.deployments.clone(),
+          servers: script_base.servers.clone(),
+        };
+        running.push(run_single_stage(
+          client,
+          stage,
+          &build_map,
+          &deployment_map,
+          notifiers,
+          &script_engine,
+          script_ctx,
+          store,
+          run_id,
+          rollback_enabled && stage.rollback,
+        ));
+      }
+    }
+
+    if running.is_empty() {
+      break;
+    }
+
+    match running.next().await.expect("FuturesUnordered is non-empty") {
+      (name, StageResult::Ran(Ok((outcome, report, prior_images)))) => {
+        let stage = by_name[name.as_str()];
+        if rollback_enabled && stage.rollback {
+          rollback_steps.push(RollbackStep {
+            stage_name: name.clone(),
+            action: stage.action,
+            ids: report.targets.iter().map(|timing| timing.id.clone()).collect(),
+            prior_images,
+          });
+        }
+        stage_outcomes.insert(name.clone(), outcome);
+        stage_reports.push(report);
+        completed.insert(name);
+      }
+      (name, StageResult::Skipped) => {
+        stage_outcomes.insert(
+          name.clone(),
+          StageOutcome {
+            success: true,
+            target_count: 0,
+            elapsed_ms: 0,
+          },
+        );
+        stage_reports.push(StageReport {
+          name: name.clone(),
+          action: by_name[name.as_str()].action.to_string(),
+          target_count: 0,
+          duration_ms: 0,
+          targets: Vec::new(),
+          skipped: true,
+        });
+        completed.insert(name);
+      }
+      (name, StageResult::Ran(Err(error))) => {
+        info!("stage {name} failed, no new stages will be scheduled: {error:#}");
+        failed.push(name);
+        aborted = true;
+        skipped.extend(pending.drain());
+      }
+    }
+  }
+
+  info!("run summary — completed: {completed:?}, failed: {failed:?}, skipped: {skipped:?}");
+
+  if let Some(name) = failed.into_iter().next() {
+    // first stage to fail, in the order failures actually arrived
+    if rollback_enabled {
+      if let Err(error) = rollback(client, monitor_name, &rollback_steps, notifiers).await {
+        info!("rollback did not complete cleanly: {error:#}");
+      }
+    }
+    return Err(anyhow!("stage {name} failed. see log for details"));
+  }
+
+  Ok(stage_reports)
+}
+
+struct RollbackStep {
+  stage_name: String,
+  action: Action,
+  ids: Vec<String>,
+  // for deploy/destroy_container stages, each deployment's image as last
+  // recorded in history by a run before this one; deployments with no
+  // recorded image are absent and are not restored
+  prior_images: HashMap<String, monitor_client::DeploymentImage>,
+}
+
+async fn rollback(
+  client: &MonitorClient,
+  monitor_name: &str,
+  steps: &[RollbackStep],
+  notifiers: &[Box<dyn Notifier>],
+) -> anyhow::Result<()> {
+  if steps.is_empty() {
+    return Ok(());
+  }
+
+  info!("rolling back {} completed stage(s)... ⏪", steps.len());
+  notify_all(
+    notifiers,
+    NotifyEvent::RollbackStarted {
+      monitor: monitor_name.to_string(),
+      stage_count: steps.len(),
+    },
+  )
+  .await;
+
+  let mut any_failed = false;
+
+  for step in steps.iter().rev() {
+    let result = match step.action {
+      Action::StartContainer => stop_containers_in_parallel(client, &step.ids).await.map(|_| ()),
+      Action::DestroyContainer | Action::Deploy => {
+        restore_deployment_images(client, &step.prior_images).await.map(|_| ())
+      }
+      Action::StopContainer | Action::Build => {
+        info!(
+          "no inverse defined for {} stage: {}, leaving as-is ⏭",
+          step.action, step.stage_name
+        );
+        continue;
+      }
+    };
+
+    match result {
+      Ok(()) => {
+        info!("rolled back stage: {} ⏪", step.stage_name);
+        notify_all(
+          notifiers,
+          NotifyEvent::RollbackStageSucceeded {
+            stage: step.stage_name.clone(),
+          },
+        )
+        .await;
+      }
+      Err(error) => {
+        any_failed = true;
+        info!("failed to roll back stage {}: {error:#}", step.stage_name);
+        notify_all(notifiers, NotifyEvent::rollback_failed(step.stage_name.clone(), &error)).await;
+      }
+    }
+  }
+
+  notify_all(
+    notifiers,
+    NotifyEvent::RollbackFinished {
+      monitor: monitor_name.to_string(),
+    },
+  )
+  .await;
+
+  if any_failed {
+    return Err(anyhow!("one or more stages failed to roll back, see log for details"));
+  }
+
+  Ok(())
+}
+
+// the image each deployment is running right now, used to persist a baseline
+// into history once a deploy succeeds — not for rollback, which must look at
+// what was there *before* this run, not mid-run
+async fn deployment_image_snapshot(
+  client: &MonitorClient,
+  deployment_ids: &[String],
+) -> anyhow::Result<HashMap<String, monitor_client::DeploymentImage>> {
+  Ok(
+    client
+      .list_deployments(None)
+      .await
+      .context("failed to snapshot deployment images")?
+      .into_iter()
+      .filter(|d| deployment_ids.contains(&d.deployment.id))
+      .map(|d| (d.deployment.id, d.deployment.image))
+      .collect(),
+  )
+}
+
+// the image each deployment was running as of the last run before this one,
+// recorded in history by a prior successful deploy — this, not a live
+// snapshot, is what `--rollback` restores
+fn prior_deployment_images(
+  store: &Store,
+  run_id: i64,
+  deployment_ids: &[String],
+) -> anyhow::Result<PriorImages> {
+  let mut images = PriorImages::new();
+  for id in deployment_ids {
+    if let Some(image) = store.last_deployment_image(id, run_id)? {
+      images.insert(id.clone(), image);
+    }
+  }
+  Ok(images)
+}
+
+async fn restore_deployment_images(
+  client: &MonitorClient,
+  prior_images: &HashMap<String, monitor_client::DeploymentImage>,
+) -> anyhow::Result<Vec<TargetTiming>> {
+  for (id, image) in prior_images {
+    client
+      .set_deployment_image(id, image.clone())
+      .await
+      .with_context(|| format!("failed to restore prior image for deployment {id}"))?;
+  }
+  let ids = prior_images.keys().cloned().collect::<Vec<_>>();
+  redeploy_deployments_in_parallel(client, &ids).await
+}
+
+fn to_named_ids(name_to_id_map: &HashMap<String, String>) -> Vec<NamedId> {
+  name_to_id_map
+    .iter()
+    .map(|(name, id)| NamedId {
+      name: name.clone(),
+      id: id.clone(),
+    })
+    .collect()
+}
+
+type PriorImages = HashMap<String, monitor_client::DeploymentImage>;
+
+enum StageResult {
+  Ran(anyhow::Result<(StageOutcome, StageReport, PriorImages)>),
+  Skipped,
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_single_stage(
+  client: &MonitorClient,
+  stage: &Stage,
+  build_map: &HashMap<String, String>,
+  deployment_map: &HashMap<String, String>,
+  notifiers: &[Box<dyn Notifier>],
+  script_engine: &ScriptEngine,
+  script_ctx: ScriptContext,
+  store: &Store,
+  run_id: i64,
+  capture_rollback_state: bool,
+) -> (String, StageResult) {
+  let Stage {
     name,
     action,
     targets,
-  } in stages
-  {
-    info!("running {action} stage: {name}... ⏳");
-    let targets = match action {
-      Action::Build => names_to_ids(&targets, &build_map)?,
-      _ => names_to_ids(&targets, &deployment_map)?,
-    };
-    match action {
-      Action::Build => {
-        trigger_builds_in_parallel(client, &targets).await?;
+    when,
+    wait_healthy,
+    ..
+  } = stage;
+
+  if let Some(when) = when {
+    match script_engine.eval_when(when, &script_ctx) {
+      Ok(true) => {}
+      Ok(false) => {
+        info!("skipping {action} stage: {name} (when evaluated to false) ⏭");
+        if let Err(error) =
+          store.record_stage(run_id, name, &action.to_string(), targets, &[], "skipped", 0, None)
+        {
+          info!("failed to record skipped stage {name} in history: {error:#}");
+        }
+        return (name.clone(), StageResult::Skipped);
       }
-      Action::Deploy => {
-        redeploy_deployments_in_parallel(client, &targets).await?;
+      Err(error) => {
+        return (name.clone(), StageResult::Ran(Err(error)));
       }
-      Action::StartContainer => start_containers_in_parallel(client, &targets).await?,
-      Action::StopContainer => stop_containers_in_parallel(client, &targets).await?,
-      Action::DestroyContainer => {
-        destroy_containers_in_parallel(client, &targets).await?;
+    }
+  }
+
+  info!("running {action} stage: {name}... ⏳");
+  notify_all(
+    notifiers,
+    NotifyEvent::StageStarted { stage: name.clone() },
+  )
+  .await;
+
+  let start = Instant::now();
+  let result = run_single_stage_inner(
+    client,
+    *action,
+    targets,
+    build_map,
+    deployment_map,
+    script_engine,
+    &script_ctx,
+    wait_healthy.as_ref(),
+    capture_rollback_state,
+    store,
+    run_id,
+  )
+  .await;
+  let duration_ms = start.elapsed().as_millis() as i64;
+
+  match result {
+    Ok((timings, prior_images)) => {
+      let ids = timings
+        .iter()
+        .map(|timing| timing.id.clone())
+        .collect::<Vec<_>>();
+      let outcome = StageOutcome {
+        success: true,
+        target_count: ids.len() as i64,
+        elapsed_ms: duration_ms,
+      };
+      let report = StageReport {
+        name: name.clone(),
+        action: action.to_string(),
+        target_count: ids.len(),
+        duration_ms: duration_ms as u128,
+        targets: timings,
+        skipped: false,
+      };
+      if let Err(error) = store.record_stage(
+        run_id,
+        name,
+        &action.to_string(),
+        targets,
+        &ids,
+        "success",
+        duration_ms,
+        None,
+      ) {
+        info!("failed to record stage {name} in history: {error:#}");
+      }
+      notify_all(
+        notifiers,
+        NotifyEvent::StageSucceeded {
+          stage: name.clone(),
+          elapsed_ms: duration_ms as u128,
+          target_count: ids.len(),
+        },
+      )
+      .await;
+      info!("finished {action} stage: {name} ✅");
+      (name.clone(), StageResult::Ran(Ok((outcome, report, prior_images))))
+    }
+    Err(error) => {
+      if let Err(record_error) = store.record_stage(
+        run_id,
+        name,
+        &action.to_string(),
+        targets,
+        &[],
+        "failed",
+        duration_ms,
+        Some(&error.to_string()),
+      ) {
+        info!("failed to record stage {name} in history: {record_error:#}");
       }
+      notify_all(notifiers, NotifyEvent::failed(name.clone(), &error)).await;
+      let error = error.context(format!("failed during {action} stage: {name}"));
+      (name.clone(), StageResult::Ran(Err(error)))
     }
-    info!("finished {action} stage: {name} ✅");
   }
-  Ok(())
 }
 
-pub async fn redeploy_deployments_in_parallel(
+#[allow(clippy::too_many_arguments)]
+async fn run_single_stage_inner(
+  client: &MonitorClient,
+  action: Action,
+  targets: &[String],
+  build_map: &HashMap<String, String>,
+  deployment_map: &HashMap<String, String>,
+  script_engine: &ScriptEngine,
+  script_ctx: &ScriptContext,
+  wait_healthy: Option<&WaitHealthyConfig>,
+  capture_rollback_state: bool,
+  store: &Store,
+  run_id: i64,
+) -> anyhow::Result<(Vec<TargetTiming>, PriorImages)> {
+  let mut names = Vec::with_capacity(targets.len());
+  for target in targets {
+    match parse_target(target) {
+      TargetSpec::Literal(name) => names.push(name.to_string()),
+      TargetSpec::Expr(expr) => {
+        names.extend(script_engine.expand_targets(expr, script_ctx)?);
+      }
+    }
+  }
+
+  let ids = match action {
+    Action::Build => names_to_ids(&names, build_map)?,
+    _ => names_to_ids(&names, deployment_map)?,
+  };
+
+  // rollback must restore what a deployment was running before *this* run,
+  // so the prior image comes from history, not a live pre-action snapshot
+  let prior_images = if capture_rollback_state
+    && matches!(action, Action::Deploy | Action::DestroyContainer)
+  {
+    prior_deployment_images(store, run_id, &ids)?
+  } else {
+    PriorImages::new()
+  };
+
+  let timings = match action {
+    Action::Build => trigger_builds_in_parallel(client, &ids).await?,
+    Action::Deploy => redeploy_deployments_in_parallel(client, &ids).await?,
+    Action::StartContainer => start_containers_in_parallel(client, &ids).await?,
+    Action::StopContainer => stop_containers_in_parallel(client, &ids).await?,
+    Action::DestroyContainer => destroy_containers_in_parallel(client, &ids).await?,
+  };
+
+  if let (Action::Deploy | Action::StartContainer, Some(wait_healthy)) = (action, wait_healthy) {
+    wait_for_all_healthy(client, &ids, wait_healthy).await?;
+  }
+
+  // a deploy just became the new known-good state; persist it so a future
+  // run's rollback can restore to it
+  if let Action::Deploy = action {
+    for (id, image) in deployment_image_snapshot(client, &ids).await? {
+      if let Err(error) = store.record_deployment_image(&id, &image, run_id) {
+        info!("failed to record deployment image baseline for {id}: {error:#}");
+      }
+    }
+  }
+
+  Ok((timings, prior_images))
+}
+
+async fn wait_for_all_healthy(
   client: &MonitorClient,
   deployment_ids: &[String],
+  config: &WaitHealthyConfig,
 ) -> anyhow::Result<()> {
-  let futes = deployment_ids.iter().map(|id| async move {
+  let futes = deployment_ids
+    .iter()
+    .map(|id| health::wait_for_healthy(client, id, config));
+  join_all(futes).await.into_iter().collect()
+}
+
+async fn run_timed_in_parallel<F, Fut>(
+  ids: &[String],
+  failure_verb: &str,
+  action: F,
+) -> anyhow::Result<Vec<TargetTiming>>
+where
+  F: Fn(String) -> Fut,
+  Fut: std::future::Future<Output = anyhow::Result<()>>,
+{
+  let futes = ids.iter().map(|id| {
+    let action = &action;
+    async move {
+      let start = Instant::now();
+      let result = action(id.clone()).await;
+      let timing = TargetTiming {
+        id: id.clone(),
+        success: result.is_ok(),
+        duration_ms: start.elapsed().as_millis(),
+      };
+      (timing, result)
+    }
+  });
+  let results = join_all(futes).await;
+  let timings = results.iter().map(|(timing, _)| timing.clone()).collect();
+  results
+    .into_iter()
+    .map(|(_, result)| result)
+    .collect::<anyhow::Result<()>>()
+    .with_context(|| format!("failed to {failure_verb} one or more targets"))?;
+  Ok(timings)
+}
+
+pub async fn redeploy_deployments_in_parallel(
+  client: &MonitorClient,
+  deployment_ids: &[String],
+) -> anyhow::Result<Vec<TargetTiming>> {
+  run_timed_in_parallel(deployment_ids, "deploy", |id| async move {
     client
-      .deploy_container(id)
+      .deploy_container(&id)
       .await
       .with_context(|| format!("failed to deploy {id}"))
       .and_then(|update| {
@@ -77,17 +706,17 @@ pub async fn redeploy_deployments_in_parallel(
           ))
         }
       })
-  });
-  join_all(futes).await.into_iter().collect()
+  })
+  .await
 }
 
 pub async fn start_containers_in_parallel(
   client: &MonitorClient,
   deployment_ids: &[String],
-) -> anyhow::Result<()> {
-  let futes = deployment_ids.iter().map(|id| async move {
+) -> anyhow::Result<Vec<TargetTiming>> {
+  run_timed_in_parallel(deployment_ids, "start", |id| async move {
     client
-      .start_container(id)
+      .start_container(&id)
       .await
       .with_context(|| format!("failed to start container {id}"))
       .and_then(|update| {
@@ -99,17 +728,17 @@ pub async fn start_containers_in_parallel(
           ))
         }
       })
-  });
-  join_all(futes).await.into_iter().collect()
+  })
+  .await
 }
 
 pub async fn stop_containers_in_parallel(
   client: &MonitorClient,
   deployment_ids: &[String],
-) -> anyhow::Result<()> {
-  let futes = deployment_ids.iter().map(|id| async move {
+) -> anyhow::Result<Vec<TargetTiming>> {
+  run_timed_in_parallel(deployment_ids, "stop", |id| async move {
     client
-      .stop_container(id)
+      .stop_container(&id)
       .await
       .with_context(|| format!("failed to stop container {id}"))
       .and_then(|update| {
@@ -121,17 +750,17 @@ pub async fn stop_containers_in_parallel(
           ))
         }
       })
-  });
-  join_all(futes).await.into_iter().collect()
+  })
+  .await
 }
 
 pub async fn destroy_containers_in_parallel(
   client: &MonitorClient,
   deployment_ids: &[String],
-) -> anyhow::Result<()> {
-  let futes = deployment_ids.iter().map(|id| async move {
+) -> anyhow::Result<Vec<TargetTiming>> {
+  run_timed_in_parallel(deployment_ids, "destroy", |id| async move {
     client
-      .remove_container(id)
+      .remove_container(&id)
       .await
       .with_context(|| format!("failed to destroy container {id}"))
       .and_then(|update| {
@@ -143,17 +772,17 @@ pub async fn destroy_containers_in_parallel(
           ))
         }
       })
-  });
-  join_all(futes).await.into_iter().collect()
+  })
+  .await
 }
 
 pub async fn trigger_builds_in_parallel(
   client: &MonitorClient,
   build_ids: &[String],
-) -> anyhow::Result<()> {
-  let futes = build_ids.iter().map(|id| async move {
+) -> anyhow::Result<Vec<TargetTiming>> {
+  run_timed_in_parallel(build_ids, "build", |id| async move {
     client
-      .build(id)
+      .build(&id)
       .await
       .with_context(|| format!("failed to build {id}"))
       .and_then(|update| {
@@ -165,8 +794,8 @@ pub async fn trigger_builds_in_parallel(
           ))
         }
       })
-  });
-  join_all(futes).await.into_iter().collect()
+  })
+  .await
 }
 
 pub async fn deployment_name_to_id_map(
@@ -181,7 +810,6 @@ pub async fn deployment_name_to_id_map(
   Ok(deployment_name_to_id_map)
 }
 
-#[allow(unused)]
 pub async fn server_name_to_id_map(
   client: &MonitorClient,
 ) -> anyhow::Result<HashMap<String, String>> {
@@ -1,23 +1,61 @@
 use std::path::PathBuf;
 
 use anyhow::Context;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use helpers::{parse_creds_file, parse_monitor_file};
 use monitor_client::MonitorClient;
 use serde::Deserialize;
+use store::Store;
 use strum::Display;
 use tracing::info;
 
+use health::WaitHealthyConfig;
+use notifier::NotifierConfig;
+
 use crate::helpers::{run_stages, wait_for_enter};
 
+mod health;
 mod helpers;
+mod notifier;
+mod report;
+mod scripting;
+mod store;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct CliArgs {
-  path: PathBuf,
-  #[arg(default_value_t = String::from("./creds.toml"))]
-  creds: String,
+  #[command(subcommand)]
+  command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+  /// Run a monitor file's stages
+  Run {
+    path: PathBuf,
+    #[arg(default_value_t = String::from("./creds.toml"))]
+    creds: String,
+    /// write a JSON report of stage/target timings to this path after the run completes
+    #[arg(long)]
+    report: Option<PathBuf>,
+    /// on failure, walk back through the already-completed stages in reverse
+    /// and apply the inverse action for each, to undo a half-applied run
+    #[arg(long)]
+    rollback: bool,
+  },
+  /// Inspect past runs recorded in the local history database
+  History {
+    #[command(subcommand)]
+    command: HistoryCommand,
+  },
+}
+
+#[derive(Subcommand, Debug)]
+enum HistoryCommand {
+  /// List past runs, most recent first
+  List,
+  /// Show the stages of a specific run
+  Show { run_id: i64 },
 }
 
 #[derive(Debug, Deserialize)]
@@ -25,22 +63,47 @@ pub struct CredsFile {
   pub url: String,
   pub username: String,
   pub secret: String,
+  #[serde(default, rename = "notifier")]
+  pub notifiers: Vec<NotifierConfig>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct MonitorFile {
   pub name: String,
   pub stage: Vec<Stage>,
+  #[serde(default, rename = "notifier")]
+  pub notifiers: Vec<NotifierConfig>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Stage {
   pub name: String,
   pub action: Action,
+  /// literal target names, or `$<rhai expression>` entries that expand to a
+  /// dynamic list of names (e.g. `$deployments.filter(|d| d.name.starts_with("api-"))`)
   pub targets: Vec<String>,
+  /// names of other stages that must complete successfully before this one is scheduled
+  #[serde(default)]
+  pub depends_on: Vec<String>,
+  /// rhai expression guarding whether this stage runs at all
+  #[serde(default)]
+  pub when: Option<String>,
+  /// for `deploy`/`start_container` stages, poll the resulting containers
+  /// until they report healthy before considering the stage successful
+  #[serde(default)]
+  pub wait_healthy: Option<WaitHealthyConfig>,
+  /// whether this stage is undone by `--rollback` if any stage of the run fails.
+  /// defaults to `true`; set to `false` to opt a stage out of rollback.
+  /// `build` and `stop_container` stages have no inverse and are never undone
+  #[serde(default = "default_true")]
+  pub rollback: bool,
 }
 
-#[derive(Debug, Deserialize, Display)]
+fn default_true() -> bool {
+  true
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Display)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum Action {
@@ -53,12 +116,28 @@ pub enum Action {
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-  let CliArgs { path, creds } = CliArgs::parse();
+  match CliArgs::parse().command {
+    Command::Run {
+      path,
+      creds,
+      report,
+      rollback,
+    } => run(path, creds, report, rollback).await,
+    Command::History { command } => history(command),
+  }
+}
 
+async fn run(
+  path: PathBuf,
+  creds: String,
+  report_path: Option<PathBuf>,
+  rollback: bool,
+) -> anyhow::Result<()> {
   let CredsFile {
     url,
     username,
     secret,
+    notifiers: creds_notifiers,
   } = parse_creds_file(creds).context("failed to parse credentials file")?;
 
   let client = MonitorClient::new_with_secret(&url, username, secret)
@@ -68,19 +147,76 @@ async fn main() -> anyhow::Result<()> {
   let MonitorFile {
     name,
     stage: stages,
+    notifiers: monitor_notifiers,
   } = parse_monitor_file(&path).context("failed to parse monitor file")?;
 
+  let notifiers = creds_notifiers
+    .iter()
+    .chain(monitor_notifiers.iter())
+    .map(NotifierConfig::build)
+    .collect::<Vec<_>>();
+
+  let store = Store::open(Store::default_path()).context("failed to open history database")?;
+
   info!("{name}");
   info!("path: {path:?}");
   println!("{stages:#?}");
 
   wait_for_enter()?;
 
-  run_stages(&client, stages)
-    .await
-    .context("failed during a stage. terminating run.")?;
+  run_stages(
+    &client,
+    &name,
+    &path,
+    stages,
+    &notifiers,
+    &store,
+    report_path.as_deref(),
+    rollback,
+  )
+  .await
+  .context("failed during a stage. terminating run.")?;
 
   info!("finished successfully ✅");
 
   Ok(())
 }
+
+fn history(command: HistoryCommand) -> anyhow::Result<()> {
+  let store = Store::open(Store::default_path()).context("failed to open history database")?;
+
+  match command {
+    HistoryCommand::List => {
+      for run in store.list_runs().context("failed to list run history")? {
+        println!(
+          "{:>4}  {:<20}  {:<30}  {}  started {}  finished {}",
+          run.id,
+          run.monitor_name,
+          run.monitor_path,
+          run.status,
+          run.started_at,
+          run.finished_at.as_deref().unwrap_or("-"),
+        );
+      }
+    }
+    HistoryCommand::Show { run_id } => {
+      for stage in store
+        .list_stages(run_id)
+        .context("failed to list stage history")?
+      {
+        println!(
+          "{:>4}  {:<20}  {:<16}  {}  {}ms  targets: {}  error: {}",
+          stage.id,
+          stage.stage_name,
+          stage.action,
+          stage.status,
+          stage.duration_ms,
+          stage.targets,
+          stage.error.as_deref().unwrap_or("-"),
+        );
+      }
+    }
+  }
+
+  Ok(())
+}
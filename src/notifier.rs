@@ -0,0 +1,247 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotifyEvent {
+  RunStarted {
+    monitor: String,
+  },
+  StageStarted {
+    stage: String,
+  },
+  StageSucceeded {
+    stage: String,
+    elapsed_ms: u128,
+    target_count: usize,
+  },
+  StageFailed {
+    stage: String,
+    error_chain: Vec<String>,
+  },
+  RunFinished {
+    monitor: String,
+  },
+  RunAborted {
+    monitor: String,
+    error_chain: Vec<String>,
+  },
+  RollbackStarted {
+    monitor: String,
+    stage_count: usize,
+  },
+  RollbackStageSucceeded {
+    stage: String,
+  },
+  RollbackStageFailed {
+    stage: String,
+    error_chain: Vec<String>,
+  },
+  RollbackFinished {
+    monitor: String,
+  },
+}
+
+impl NotifyEvent {
+  pub fn failed(stage: impl Into<String>, error: &anyhow::Error) -> NotifyEvent {
+    NotifyEvent::StageFailed {
+      stage: stage.into(),
+      error_chain: error_chain(error),
+    }
+  }
+
+  pub fn aborted(monitor: impl Into<String>, error: &anyhow::Error) -> NotifyEvent {
+    NotifyEvent::RunAborted {
+      monitor: monitor.into(),
+      error_chain: error_chain(error),
+    }
+  }
+
+  pub fn rollback_failed(stage: impl Into<String>, error: &anyhow::Error) -> NotifyEvent {
+    NotifyEvent::RollbackStageFailed {
+      stage: stage.into(),
+      error_chain: error_chain(error),
+    }
+  }
+
+  pub fn summary(&self) -> String {
+    match self {
+      NotifyEvent::RunStarted { monitor } => format!("🚀 run started: {monitor}"),
+      NotifyEvent::StageStarted { stage } => format!("⏳ stage started: {stage}"),
+      NotifyEvent::StageSucceeded {
+        stage,
+        elapsed_ms,
+        target_count,
+      } => {
+        format!("✅ stage succeeded: {stage} ({target_count} targets, {elapsed_ms}ms)")
+      }
+      NotifyEvent::StageFailed { stage, error_chain } => {
+        format!("❌ stage failed: {stage}: {}", error_chain.join(": "))
+      }
+      NotifyEvent::RunFinished { monitor } => format!("✅ run finished: {monitor}"),
+      NotifyEvent::RunAborted { monitor, error_chain } => {
+        format!("❌ run aborted: {monitor}: {}", error_chain.join(": "))
+      }
+      NotifyEvent::RollbackStarted { monitor, stage_count } => {
+        format!("⏪ rolling back {stage_count} completed stage(s) for {monitor}")
+      }
+      NotifyEvent::RollbackStageSucceeded { stage } => {
+        format!("⏪ rolled back stage: {stage}")
+      }
+      NotifyEvent::RollbackStageFailed { stage, error_chain } => {
+        format!("❌ failed to roll back stage: {stage}: {}", error_chain.join(": "))
+      }
+      NotifyEvent::RollbackFinished { monitor } => format!("⏪ rollback finished: {monitor}"),
+    }
+  }
+}
+
+fn error_chain(error: &anyhow::Error) -> Vec<String> {
+  error.chain().map(|cause| cause.to_string()).collect()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotifierConfig {
+  Webhook { url: String },
+  Slack { url: String },
+  Email {
+    host: String,
+    port: u16,
+    username: String,
+    password: String,
+    from: String,
+    to: String,
+  },
+}
+
+impl NotifierConfig {
+  pub fn build(&self) -> Box<dyn Notifier> {
+    match self {
+      NotifierConfig::Webhook { url } => Box::new(WebhookNotifier { url: url.clone() }),
+      NotifierConfig::Slack { url } => Box::new(SlackNotifier { url: url.clone() }),
+      NotifierConfig::Email {
+        host,
+        port,
+        username,
+        password,
+        from,
+        to,
+      } => Box::new(EmailNotifier {
+        host: host.clone(),
+        port: *port,
+        username: username.clone(),
+        password: password.clone(),
+        from: from.clone(),
+        to: to.clone(),
+      }),
+    }
+  }
+}
+
+#[async_trait]
+pub trait Notifier: Send + Sync {
+  async fn notify(&self, event: &NotifyEvent) -> anyhow::Result<()>;
+}
+
+pub struct WebhookNotifier {
+  url: String,
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+  async fn notify(&self, event: &NotifyEvent) -> anyhow::Result<()> {
+    Client::new()
+      .post(&self.url)
+      .timeout(Duration::from_secs(10))
+      .json(event)
+      .send()
+      .await
+      .context("failed to send webhook notification")?
+      .error_for_status()
+      .context("webhook notification returned an error status")?;
+    Ok(())
+  }
+}
+
+pub struct SlackNotifier {
+  url: String,
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+  async fn notify(&self, event: &NotifyEvent) -> anyhow::Result<()> {
+    Client::new()
+      .post(&self.url)
+      .timeout(Duration::from_secs(10))
+      .json(&serde_json::json!({ "text": event.summary() }))
+      .send()
+      .await
+      .context("failed to send slack notification")?
+      .error_for_status()
+      .context("slack notification returned an error status")?;
+    Ok(())
+  }
+}
+
+pub struct EmailNotifier {
+  host: String,
+  port: u16,
+  username: String,
+  password: String,
+  from: String,
+  to: String,
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+  async fn notify(&self, event: &NotifyEvent) -> anyhow::Result<()> {
+    use lettre::{
+      message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+      AsyncTransport, Message, Tokio1Executor,
+    };
+
+    let email = Message::builder()
+      .from(self.from.parse::<Mailbox>().context("invalid from address")?)
+      .to(self.to.parse::<Mailbox>().context("invalid to address")?)
+      .subject(event.summary())
+      .body(event.summary())
+      .context("failed to build email")?;
+
+    let creds = Credentials::new(self.username.clone(), self.password.clone());
+    // port 465 expects implicit TLS (`relay`); everything else (587, 25, ...)
+    // expects the client to connect in plaintext and upgrade via STARTTLS.
+    let builder = if self.port == 465 {
+      AsyncSmtpTransport::<Tokio1Executor>::relay(&self.host)
+    } else {
+      AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&self.host)
+    }
+    .context("failed to configure smtp relay")?;
+    let mailer: AsyncSmtpTransport<Tokio1Executor> =
+      builder.port(self.port).credentials(creds).build();
+
+    mailer
+      .send(email)
+      .await
+      .context("failed to send email notification")?;
+    Ok(())
+  }
+}
+
+// ignore individual failures so a single broken webhook can't abort a run
+pub async fn notify_all(notifiers: &[Box<dyn Notifier>], event: NotifyEvent) {
+  let futes = notifiers.iter().map(|notifier| {
+    let event = &event;
+    async move {
+      if let Err(error) = notifier.notify(event).await {
+        warn!("failed to deliver notification: {error:#}");
+      }
+    }
+  });
+  monitor_client::futures_util::future::join_all(futes).await;
+}
@@ -0,0 +1,81 @@
+use std::path::Path;
+
+use anyhow::Context;
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct EnvironmentInfo {
+  pub hostname: String,
+  pub os: String,
+  pub monrun_version: String,
+  pub git_commit: Option<String>,
+}
+
+impl EnvironmentInfo {
+  pub fn collect(monitor_path: &Path) -> Self {
+    EnvironmentInfo {
+      hostname: hostname(),
+      os: std::env::consts::OS.to_string(),
+      monrun_version: env!("CARGO_PKG_VERSION").to_string(),
+      git_commit: git_commit_for(monitor_path),
+    }
+  }
+}
+
+fn hostname() -> String {
+  hostname::get()
+    .map(|name| name.to_string_lossy().to_string())
+    .unwrap_or_else(|_| "unknown".to_string())
+}
+
+fn git_commit_for(monitor_path: &Path) -> Option<String> {
+  let parent = monitor_path.parent().filter(|dir| !dir.as_os_str().is_empty());
+  let dir = match parent {
+    Some(dir) => dir.to_path_buf(),
+    None => std::env::current_dir().ok()?,
+  };
+  let output = std::process::Command::new("git")
+    .args(["rev-parse", "HEAD"])
+    .current_dir(dir)
+    .output()
+    .ok()?;
+  if !output.status.success() {
+    return None;
+  }
+  String::from_utf8(output.stdout)
+    .ok()
+    .map(|commit| commit.trim().to_string())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetTiming {
+  pub id: String,
+  pub success: bool,
+  pub duration_ms: u128,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StageReport {
+  pub name: String,
+  pub action: String,
+  pub target_count: usize,
+  pub duration_ms: u128,
+  pub targets: Vec<TargetTiming>,
+  /// true if the stage's `when` condition evaluated to false and it never ran
+  pub skipped: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+  pub environment: EnvironmentInfo,
+  pub monitor_name: String,
+  pub stages: Vec<StageReport>,
+}
+
+impl Report {
+  pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+    let json = serde_json::to_string_pretty(self).context("failed to serialize report")?;
+    std::fs::write(path, json).context("failed to write report file")?;
+    Ok(())
+  }
+}
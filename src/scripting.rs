@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context};
+use rhai::{Array, Engine, Scope};
+
+#[derive(Debug, Clone)]
+pub struct StageOutcome {
+  pub success: bool,
+  pub target_count: i64,
+  pub elapsed_ms: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct NamedId {
+  pub name: String,
+  pub id: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ScriptContext {
+  pub stages: HashMap<String, StageOutcome>,
+  pub builds: Vec<NamedId>,
+  pub deployments: Vec<NamedId>,
+  pub servers: Vec<NamedId>,
+}
+
+pub struct ScriptEngine {
+  engine: Engine,
+}
+
+impl ScriptEngine {
+  pub fn new() -> Self {
+    let mut engine = Engine::new();
+
+    engine
+      .register_type_with_name::<NamedId>("NamedId")
+      .register_get("name", |n: &mut NamedId| n.name.clone())
+      .register_get("id", |n: &mut NamedId| n.id.clone());
+
+    engine
+      .register_type_with_name::<StageOutcome>("StageOutcome")
+      .register_get("success", |s: &mut StageOutcome| s.success)
+      .register_get("target_count", |s: &mut StageOutcome| s.target_count)
+      .register_get("elapsed_ms", |s: &mut StageOutcome| s.elapsed_ms);
+
+    ScriptEngine { engine }
+  }
+
+  fn scope_for(&self, ctx: &ScriptContext) -> Scope<'static> {
+    let as_array =
+      |items: &[NamedId]| -> Array { items.iter().cloned().map(rhai::Dynamic::from).collect() };
+
+    let mut scope = Scope::new();
+    scope.push("builds", as_array(&ctx.builds));
+    scope.push("deployments", as_array(&ctx.deployments));
+    scope.push("servers", as_array(&ctx.servers));
+
+    let mut stages = rhai::Map::new();
+    for (name, outcome) in &ctx.stages {
+      stages.insert(name.as_str().into(), rhai::Dynamic::from(outcome.clone()));
+    }
+    scope.push("stages", stages);
+
+    scope
+  }
+
+  pub fn eval_when(&self, expr: &str, ctx: &ScriptContext) -> anyhow::Result<bool> {
+    let mut scope = self.scope_for(ctx);
+    self
+      .engine
+      .eval_with_scope::<bool>(&mut scope, expr)
+      .map_err(|error| anyhow!("failed to evaluate `when` expression `{expr}`: {error}"))
+  }
+
+  pub fn expand_targets(&self, expr: &str, ctx: &ScriptContext) -> anyhow::Result<Vec<String>> {
+    let mut scope = self.scope_for(ctx);
+    let result: Array = self
+      .engine
+      .eval_with_scope(&mut scope, expr)
+      .map_err(|error| anyhow!("failed to evaluate target expression `{expr}`: {error}"))?;
+
+    result
+      .into_iter()
+      .map(|value| {
+        if let Some(named) = value.clone().try_cast::<NamedId>() {
+          Ok(named.name)
+        } else {
+          value
+            .into_string()
+            .map_err(|ty| anyhow!("target expression `{expr}` produced a non-string value: {ty}"))
+        }
+      })
+      .collect::<anyhow::Result<Vec<_>>>()
+      .context("failed to collect expanded targets")
+  }
+}
+
+impl Default for ScriptEngine {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+pub enum TargetSpec<'a> {
+  Literal(&'a str),
+  Expr(&'a str),
+}
+
+pub fn parse_target(raw: &str) -> TargetSpec<'_> {
+  match raw.strip_prefix('$') {
+    Some(expr) => TargetSpec::Expr(expr),
+    None => TargetSpec::Literal(raw),
+  }
+}
@@ -0,0 +1,223 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use chrono::Utc;
+use monitor_client::DeploymentImage;
+use rusqlite::{params, Connection, OptionalExtension};
+
+#[derive(Debug)]
+pub struct RunRecord {
+  pub id: i64,
+  pub monitor_name: String,
+  pub monitor_path: String,
+  pub started_at: String,
+  pub finished_at: Option<String>,
+  pub status: String,
+}
+
+#[derive(Debug)]
+pub struct StageRecord {
+  pub id: i64,
+  pub run_id: i64,
+  pub stage_name: String,
+  pub action: String,
+  pub targets: String,
+  pub resolved_ids: String,
+  pub status: String,
+  pub duration_ms: i64,
+  pub error: Option<String>,
+}
+
+pub struct Store {
+  conn: Connection,
+}
+
+impl Store {
+  pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+    let conn = Connection::open(path).context("failed to open history database")?;
+    conn
+      .execute_batch(
+        "CREATE TABLE IF NOT EXISTS run (
+           id INTEGER PRIMARY KEY AUTOINCREMENT,
+           monitor_name TEXT NOT NULL,
+           monitor_path TEXT NOT NULL,
+           started_at TEXT NOT NULL,
+           finished_at TEXT,
+           status TEXT NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS stage (
+           id INTEGER PRIMARY KEY AUTOINCREMENT,
+           run_id INTEGER NOT NULL REFERENCES run(id),
+           stage_name TEXT NOT NULL,
+           action TEXT NOT NULL,
+           targets TEXT NOT NULL,
+           resolved_ids TEXT NOT NULL,
+           status TEXT NOT NULL,
+           duration_ms INTEGER NOT NULL,
+           error TEXT
+         );
+         CREATE TABLE IF NOT EXISTS deployment_image (
+           id INTEGER PRIMARY KEY AUTOINCREMENT,
+           deployment_id TEXT NOT NULL,
+           run_id INTEGER NOT NULL REFERENCES run(id),
+           image TEXT NOT NULL,
+           recorded_at TEXT NOT NULL
+         );",
+      )
+      .context("failed to run history migrations")?;
+    Ok(Store { conn })
+  }
+
+  pub fn default_path() -> PathBuf {
+    PathBuf::from("./monrun_history.sqlite")
+  }
+
+  pub fn start_run(&self, monitor_name: &str, monitor_path: &str) -> anyhow::Result<i64> {
+    self
+      .conn
+      .execute(
+        "INSERT INTO run (monitor_name, monitor_path, started_at, status) VALUES (?1, ?2, ?3, 'running')",
+        params![monitor_name, monitor_path, Utc::now().to_rfc3339()],
+      )
+      .context("failed to record run start")?;
+    Ok(self.conn.last_insert_rowid())
+  }
+
+  pub fn finish_run(&self, run_id: i64, status: &str) -> anyhow::Result<()> {
+    self
+      .conn
+      .execute(
+        "UPDATE run SET finished_at = ?1, status = ?2 WHERE id = ?3",
+        params![Utc::now().to_rfc3339(), status, run_id],
+      )
+      .context("failed to record run completion")?;
+    Ok(())
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  pub fn record_stage(
+    &self,
+    run_id: i64,
+    stage_name: &str,
+    action: &str,
+    targets: &[String],
+    resolved_ids: &[String],
+    status: &str,
+    duration_ms: i64,
+    error: Option<&str>,
+  ) -> anyhow::Result<()> {
+    self
+      .conn
+      .execute(
+        "INSERT INTO stage (run_id, stage_name, action, targets, resolved_ids, status, duration_ms, error)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+          run_id,
+          stage_name,
+          action,
+          targets.join(","),
+          resolved_ids.join(","),
+          status,
+          duration_ms,
+          error,
+        ],
+      )
+      .context("failed to record stage result")?;
+    Ok(())
+  }
+
+  pub fn list_runs(&self) -> anyhow::Result<Vec<RunRecord>> {
+    let mut stmt = self
+      .conn
+      .prepare(
+        "SELECT id, monitor_name, monitor_path, started_at, finished_at, status
+         FROM run ORDER BY id DESC",
+      )
+      .context("failed to prepare run history query")?;
+    let runs = stmt
+      .query_map([], |row| {
+        Ok(RunRecord {
+          id: row.get(0)?,
+          monitor_name: row.get(1)?,
+          monitor_path: row.get(2)?,
+          started_at: row.get(3)?,
+          finished_at: row.get(4)?,
+          status: row.get(5)?,
+        })
+      })
+      .context("failed to query run history")?
+      .collect::<Result<Vec<_>, _>>()
+      .context("failed to read run history rows")?;
+    Ok(runs)
+  }
+
+  pub fn list_stages(&self, run_id: i64) -> anyhow::Result<Vec<StageRecord>> {
+    let mut stmt = self
+      .conn
+      .prepare(
+        "SELECT id, run_id, stage_name, action, targets, resolved_ids, status, duration_ms, error
+         FROM stage WHERE run_id = ?1 ORDER BY id ASC",
+      )
+      .context("failed to prepare stage history query")?;
+    let stages = stmt
+      .query_map(params![run_id], |row| {
+        Ok(StageRecord {
+          id: row.get(0)?,
+          run_id: row.get(1)?,
+          stage_name: row.get(2)?,
+          action: row.get(3)?,
+          targets: row.get(4)?,
+          resolved_ids: row.get(5)?,
+          status: row.get(6)?,
+          duration_ms: row.get(7)?,
+          error: row.get(8)?,
+        })
+      })
+      .context("failed to query stage history")?
+      .collect::<Result<Vec<_>, _>>()
+      .context("failed to read stage history rows")?;
+    Ok(stages)
+  }
+
+  /// record a deployment's image as of a successful deploy, so a later run
+  /// can roll back to it even after this run's own history is the newest entry
+  pub fn record_deployment_image(
+    &self,
+    deployment_id: &str,
+    image: &DeploymentImage,
+    run_id: i64,
+  ) -> anyhow::Result<()> {
+    let image = serde_json::to_string(image).context("failed to serialize deployment image")?;
+    self
+      .conn
+      .execute(
+        "INSERT INTO deployment_image (deployment_id, run_id, image, recorded_at) VALUES (?1, ?2, ?3, ?4)",
+        params![deployment_id, run_id, image, Utc::now().to_rfc3339()],
+      )
+      .context("failed to record deployment image")?;
+    Ok(())
+  }
+
+  /// the image a deployment was running as of the most recent run *before*
+  /// `run_id`, i.e. the version `--rollback` should restore it to
+  pub fn last_deployment_image(
+    &self,
+    deployment_id: &str,
+    run_id: i64,
+  ) -> anyhow::Result<Option<DeploymentImage>> {
+    let image: Option<String> = self
+      .conn
+      .query_row(
+        "SELECT image FROM deployment_image
+         WHERE deployment_id = ?1 AND run_id < ?2
+         ORDER BY id DESC LIMIT 1",
+        params![deployment_id, run_id],
+        |row| row.get(0),
+      )
+      .optional()
+      .context("failed to query prior deployment image")?;
+    image
+      .map(|image| serde_json::from_str(&image).context("failed to deserialize deployment image"))
+      .transpose()
+  }
+}